@@ -0,0 +1,343 @@
+use crate::timer::Timer;
+use crate::workload::Workload;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Running totals for the witnesses a backend has generated, aggregated
+/// across every step run with `--witness` and printed by `print_metrics`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WitnessStats {
+    pub witnesses: u64,
+    pub total_bytes: u64,
+}
+
+impl WitnessStats {
+    pub fn single(bytes: usize) -> Self {
+        WitnessStats {
+            witnesses: 1,
+            total_bytes: bytes as u64,
+        }
+    }
+
+    pub fn print(&self, backend: &str) {
+        if self.witnesses == 0 {
+            return;
+        }
+        println!(
+            "{backend}: {} witnesses, {} bytes total, {:.1} bytes/witness avg",
+            self.witnesses,
+            self.total_bytes,
+            self.total_bytes as f64 / self.witnesses as f64,
+        );
+    }
+}
+
+impl std::ops::AddAssign for WitnessStats {
+    fn add_assign(&mut self, other: Self) {
+        self.witnesses += other.witnesses;
+        self.total_bytes += other.total_bytes;
+    }
+}
+
+/// Per-step write-efficiency counters, in the spirit of net gas metering:
+/// each write is classified by comparing its new value against the value
+/// its key held at the start of the step, rather than just counting raw
+/// writes. Aggregated across steps and printed by `print_metrics`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirtyStats {
+    /// Writes that took an empty key non-empty.
+    pub creates: u64,
+    /// Writes that took a non-empty key empty.
+    pub clears: u64,
+    /// Writes whose new value matches the key's value at step start.
+    pub noops: u64,
+    /// Distinct keys touched by at least one write in the step.
+    pub dirty_keys: u64,
+}
+
+impl DirtyStats {
+    pub fn print(&self, backend: &str) {
+        if self.dirty_keys == 0 {
+            return;
+        }
+        println!(
+            "{backend}: {} dirty keys, {} creates, {} clears, {} no-op writes",
+            self.dirty_keys, self.creates, self.clears, self.noops,
+        );
+    }
+}
+
+impl std::ops::AddAssign for DirtyStats {
+    fn add_assign(&mut self, other: Self) {
+        self.creates += other.creates;
+        self.clears += other.clears;
+        self.noops += other.noops;
+        self.dirty_keys += other.dirty_keys;
+    }
+}
+
+/// A value of all-zero bytes is treated the same as absent: net metering
+/// cares about whether a slot ends up materially set, not which bytes
+/// represent "unset".
+fn is_empty(value: Option<&[u8]>) -> bool {
+    value.map_or(true, |bytes| bytes.iter().all(|&b| b == 0))
+}
+
+/// Classify a write against the value its key held at the start of the
+/// step and fold the result into `stats`. Call sites own deduplicating
+/// `dirty_keys` themselves, since that depends on how each backend tracks
+/// per-key state.
+pub(crate) fn classify_write(original: Option<&[u8]>, new: Option<&[u8]>, stats: &mut DirtyStats) {
+    let was_empty = is_empty(original);
+    let is_empty_now = is_empty(new);
+
+    if was_empty && !is_empty_now {
+        stats.creates += 1;
+    } else if !was_empty && is_empty_now {
+        stats.clears += 1;
+    } else if (was_empty && is_empty_now) || original == new {
+        stats.noops += 1;
+    }
+}
+
+/// Identifies a checkpoint pushed by [`Transaction::checkpoint`].
+///
+/// Opaque to callers beyond passing it back to `revert_to` or
+/// `discard_checkpoint`; backends are free to use it as a stack index.
+pub type CheckpointId = usize;
+
+/// A single workload step's view over a backend's committed state.
+///
+/// Implementations buffer writes in memory and only touch persistent
+/// storage when the driver commits the transaction at the end of
+/// `Database::execute`.
+pub trait Transaction {
+    fn read(&mut self, key: &[u8]) -> Option<Vec<u8>>;
+
+    /// Record that `key` was part of this step's read set, independently
+    /// of whether `read` was actually called for it. Used to build proof
+    /// targets over exactly the keys a step touched.
+    fn note_read(&mut self, key: &[u8], value: Option<Vec<u8>>);
+
+    fn write(&mut self, key: &[u8], value: Option<&[u8]>);
+
+    /// Read a contract storage slot. Backends that don't model accounts
+    /// and storage separately can leave this at its default, which just
+    /// addresses `read` with the concatenation of `addr` and `slot`.
+    fn read_slot(&mut self, addr: &[u8], slot: &[u8]) -> Option<Vec<u8>> {
+        self.read(&[addr, slot].concat())
+    }
+
+    /// Write a contract storage slot. See [`Transaction::read_slot`].
+    fn write_slot(&mut self, addr: &[u8], slot: &[u8], value: Option<&[u8]>) {
+        self.write(&[addr, slot].concat(), value)
+    }
+
+    /// Like [`Transaction::note_read`], but for a slot read via
+    /// [`Transaction::read_slot`]. See [`Transaction::read_slot`] for why
+    /// the default just addresses `note_read` with the concatenation of
+    /// `addr` and `slot`.
+    fn note_read_slot(&mut self, addr: &[u8], slot: &[u8], value: Option<Vec<u8>>) {
+        self.note_read(&[addr, slot].concat(), value)
+    }
+
+    /// Push a new write overlay and return an id that identifies it.
+    fn checkpoint(&mut self) -> CheckpointId;
+
+    /// Drop every overlay pushed at or after `checkpoint`, undoing their
+    /// writes and rolling the read set back to what it was at that point.
+    fn revert_to(&mut self, checkpoint: CheckpointId);
+
+    /// Merge the overlay pushed at `checkpoint` into the one beneath it,
+    /// keeping its writes but giving up the ability to revert to it.
+    fn discard_checkpoint(&mut self, checkpoint: CheckpointId);
+
+    /// Write-efficiency counters for every write made since this
+    /// transaction was created, classified against each key's value at
+    /// the start of the step. See [`DirtyStats`].
+    fn dirty_stats(&self) -> DirtyStats;
+}
+
+/// A backend under benchmark: owns persistent storage and drives
+/// workloads against it.
+pub trait Database {
+    fn execute(
+        &mut self,
+        timer: Option<&mut Timer>,
+        workload: &mut dyn Workload,
+        timeout: Option<std::time::Instant>,
+        witness: bool,
+    );
+
+    fn parallel_execute(
+        &mut self,
+        timer: Option<&mut Timer>,
+        thread_pool: &rayon::ThreadPool,
+        workloads: &mut [Box<dyn Workload>],
+        timeout: Option<std::time::Instant>,
+        witness: bool,
+    ) -> Result<()>;
+
+    fn print_metrics(&self);
+
+    /// Build a Merkle multiproof/witness over `reads` (the keys a step
+    /// accumulated via `Transaction::note_read`) and return its size.
+    fn prove(&self, reads: &std::collections::HashSet<Vec<u8>>) -> WitnessStats;
+
+    /// Serialize the committed state into a self-contained directory at
+    /// `path`. If `incremental_base` is given, only write what changed
+    /// since that (already-written) snapshot instead of a full copy.
+    fn snapshot(&self, path: &Path, incremental_base: Option<&Path>) -> Result<()>;
+
+    /// Reconstruct a ready-to-run database from a directory written by
+    /// `snapshot`. If `path` was written with `--incremental base`, `base`
+    /// must point at that same base snapshot so the delta can be layered
+    /// onto it. Whenever `base` is given it is loaded first and `path`
+    /// layered on top, so what `base` holds and `path` lacks is kept.
+    fn restore(&mut self, path: &Path, base: Option<&Path>) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum BackendKind {
+    Nomt,
+    SovDb,
+    SpTrie,
+    Reth,
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BackendKind::Nomt => "nomt",
+            BackendKind::SovDb => "sov_db",
+            BackendKind::SpTrie => "sp_trie",
+            BackendKind::Reth => "reth",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Recursively copy `src` into `dst`, creating `dst` if needed. Shared by
+/// the backends whose snapshot format is just their on-disk directory.
+pub(crate) fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_path = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &dst_path)?;
+        } else {
+            std::fs::copy(entry.path(), dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Like `copy_dir_all`, but only copy files under `src` that are missing
+/// from `base` or whose size/modified time differ from it. Used to build
+/// an `--incremental` snapshot on top of an already-written base one.
+pub(crate) fn copy_changed_since(src: &Path, base: &Path, dst: &Path) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let src_path = entry.path();
+        let base_path = base.join(entry.file_name());
+        let dst_path = dst.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_changed_since(&src_path, &base_path, &dst_path)?;
+            continue;
+        }
+
+        let src_meta = entry.metadata()?;
+        let unchanged = base_path
+            .metadata()
+            .ok()
+            .map(|base_meta| {
+                base_meta.len() == src_meta.len() && base_meta.modified().ok() == src_meta.modified().ok()
+            })
+            .unwrap_or(false);
+
+        if !unchanged {
+            std::fs::copy(&src_path, dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+const KV_SNAPSHOT_FILE: &str = "data";
+
+/// Write an in-memory key/value backend's state as a single flat file
+/// inside `path`, used by the toy (non-MDBX) backends' `snapshot`.
+pub(crate) fn write_kv_snapshot(path: &Path, map: &BTreeMap<Vec<u8>, Vec<u8>>) -> Result<()> {
+    std::fs::create_dir_all(path)?;
+    let mut file = std::fs::File::create(path.join(KV_SNAPSHOT_FILE))?;
+    for (key, value) in map {
+        file.write_all(&(key.len() as u32).to_le_bytes())?;
+        file.write_all(key)?;
+        file.write_all(&(value.len() as u32).to_le_bytes())?;
+        file.write_all(value)?;
+    }
+    Ok(())
+}
+
+/// Write only the entries of `map` that differ from `base`'s snapshot,
+/// for `--incremental` snapshots.
+pub(crate) fn write_kv_snapshot_incremental(
+    path: &Path,
+    base: &Path,
+    map: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Result<()> {
+    let base_map = read_kv_snapshot(base).unwrap_or_default();
+    let changed: BTreeMap<_, _> = map
+        .iter()
+        .filter(|(k, v)| base_map.get(*k) != Some(*v))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    write_kv_snapshot(path, &changed)
+}
+
+pub(crate) fn read_kv_snapshot(path: &Path) -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+    let mut file = std::fs::File::open(path.join(KV_SNAPSHOT_FILE))?;
+    let mut map = BTreeMap::new();
+    let mut len_buf = [0u8; 4];
+    loop {
+        if file.read_exact(&mut len_buf).is_err() {
+            break;
+        }
+        let mut key = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        file.read_exact(&mut key)?;
+        file.read_exact(&mut len_buf)?;
+        let mut value = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        file.read_exact(&mut value)?;
+        map.insert(key, value);
+    }
+    Ok(map)
+}
+
+impl BackendKind {
+    pub fn instantiate(
+        &self,
+        reset: bool,
+        commit_concurrency: usize,
+        io_workers: usize,
+        hashtable_buckets: u32,
+        state_cache_bytes: Option<usize>,
+    ) -> Box<dyn Database> {
+        match self {
+            BackendKind::Nomt => Box::new(crate::nomt::NomtDB::open(
+                reset,
+                commit_concurrency,
+                io_workers,
+                hashtable_buckets,
+                state_cache_bytes,
+            )),
+            BackendKind::SovDb => Box::new(crate::sov_db::SovDB::open(reset, state_cache_bytes)),
+            BackendKind::SpTrie => Box::new(crate::sp_trie::SpTrieDB::open(reset, state_cache_bytes)),
+            BackendKind::Reth => Box::new(crate::reth::RethDB::open(reset, state_cache_bytes)),
+        }
+    }
+}
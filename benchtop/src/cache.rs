@@ -0,0 +1,71 @@
+use lru::LruCache;
+
+/// A bounded read-through cache in front of a backend's persistent
+/// store, evicting least-recently-used entries once `byte_budget` is
+/// exceeded rather than capping the number of entries.
+pub struct StateCache {
+    entries: LruCache<Vec<u8>, Vec<u8>>,
+    byte_budget: usize,
+    bytes_used: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl StateCache {
+    pub fn new(byte_budget: usize) -> Self {
+        StateCache {
+            entries: LruCache::unbounded(),
+            byte_budget,
+            bytes_used: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.entries.get(key) {
+            Some(value) => {
+                self.hits += 1;
+                Some(value.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.invalidate(&key);
+        self.bytes_used += key.len() + value.len();
+        self.entries.put(key, value);
+
+        while self.bytes_used > self.byte_budget {
+            match self.entries.pop_lru() {
+                Some((key, value)) => {
+                    self.bytes_used -= key.len() + value.len();
+                    self.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn invalidate(&mut self, key: &[u8]) {
+        if let Some(value) = self.entries.pop(key) {
+            self.bytes_used -= key.len() + value.len();
+        }
+    }
+
+    pub fn print(&self, backend: &str) {
+        if self.hits + self.misses == 0 {
+            return;
+        }
+        println!(
+            "{backend} cache: {} hits, {} misses, {} evictions",
+            self.hits, self.misses, self.evictions,
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::backend::BackendKind;
+use crate::workload::WorkloadKind;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Create and populate a fresh database, then exit.
+    Init(InitParams),
+    /// Run a workload against a (freshly created or existing) database.
+    Run(RunParams),
+    /// Serialize a database's committed state into a self-contained
+    /// directory that `restore` can reconstruct it from.
+    Snapshot(SnapshotParams),
+    /// Reconstruct a database from a directory written by `snapshot`.
+    Restore(RestoreParams),
+}
+
+#[derive(Args)]
+pub struct SnapshotParams {
+    #[arg(value_enum)]
+    pub backend: BackendKind,
+    /// Directory to write the snapshot into.
+    pub path: PathBuf,
+    /// Only write pages/entries that changed since this base snapshot,
+    /// instead of a full copy.
+    #[arg(long)]
+    pub incremental: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RestoreParams {
+    #[arg(value_enum)]
+    pub backend: BackendKind,
+    /// Directory previously written by `snapshot`.
+    pub path: PathBuf,
+    /// Base snapshot `path` was written against via `snapshot --incremental`.
+    /// Required to restore an incremental snapshot, since it only contains
+    /// the entries that changed since the base.
+    #[arg(long)]
+    pub base: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct InitParams {
+    #[arg(value_enum)]
+    pub backend: BackendKind,
+    #[command(flatten)]
+    pub workload: WorkloadParams,
+}
+
+#[derive(Args)]
+pub struct RunParams {
+    #[arg(value_enum)]
+    pub backend: BackendKind,
+    #[command(flatten)]
+    pub workload: WorkloadParams,
+    #[command(flatten)]
+    pub limits: Limits,
+
+    /// Re-run `init` before the workload instead of reusing an existing db.
+    #[arg(long)]
+    pub reset: bool,
+
+    /// Run the workload once first with this deadline, discard the
+    /// timings, then run it again for real. The backends run one workload
+    /// step per call and do not check the deadline.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub warm_up: Option<Duration>,
+
+    /// Build a Merkle witness over each step's read set and report its
+    /// size/generation time alongside the commit metrics.
+    #[arg(long)]
+    pub witness: bool,
+}
+
+#[derive(Args)]
+pub struct Limits {
+    /// Stop after this many operations.
+    #[arg(long)]
+    pub ops: Option<u64>,
+    /// Deadline passed to the backend. The backends run one workload step
+    /// per call and do not check it.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub time: Option<Duration>,
+}
+
+#[derive(Args)]
+pub struct WorkloadParams {
+    /// Number of accounts/keys in the dataset.
+    #[arg(long, default_value_t = 1_000_000)]
+    pub size: u64,
+
+    #[arg(long, value_enum, default_value_t = WorkloadKind::Transfer)]
+    pub kind: WorkloadKind,
+
+    /// Fraction of pushed checkpoints that get reverted rather than kept,
+    /// only meaningful for `--kind checkpoint`.
+    #[arg(long, default_value_t = 0.3)]
+    pub revert_rate: f64,
+
+    /// Contract storage slots per account, only meaningful for
+    /// `--kind contract-storage`.
+    #[arg(long, default_value_t = 100)]
+    pub slots_per_account: u64,
+
+    #[arg(long, default_value_t = 1)]
+    pub workload_concurrency: u32,
+    #[arg(long, default_value_t = 1)]
+    pub commit_concurrency: usize,
+    #[arg(long, default_value_t = 1)]
+    pub io_workers: usize,
+    #[arg(long, default_value_t = 1_000_000)]
+    pub hashtable_buckets: u32,
+
+    /// Wrap reads in a bounded read-through LRU cache of this many bytes.
+    /// Unset disables the cache entirely.
+    #[arg(long)]
+    pub state_cache_bytes: Option<usize>,
+}
@@ -0,0 +1,124 @@
+use crate::backend::Transaction;
+use crate::workload::Workload;
+use rand::Rng;
+
+/// Exercises nested checkpoint/revert the way an abort-and-retry state
+/// machine does: pushes a random depth of checkpoints, does one read and
+/// one write at each level, then reverts a tunable fraction of them instead of
+/// discarding.
+pub struct CheckpointWorkload {
+    size: u64,
+    ops_remaining: u64,
+    revert_rate: f64,
+}
+
+impl CheckpointWorkload {
+    pub fn new(size: u64, max_ops: u64, revert_rate: f64) -> Self {
+        CheckpointWorkload {
+            size,
+            ops_remaining: max_ops,
+            revert_rate,
+        }
+    }
+}
+
+impl Workload for CheckpointWorkload {
+    fn run_step(&mut self, transaction: &mut dyn Transaction) {
+        let mut rng = rand::thread_rng();
+
+        let depth = rng.gen_range(1..=4);
+        let mut checkpoints = Vec::with_capacity(depth);
+
+        for _ in 0..depth {
+            if self.ops_remaining == 0 {
+                break;
+            }
+            self.ops_remaining -= 1;
+
+            checkpoints.push(transaction.checkpoint());
+
+            let key = rng.gen_range(0..self.size).to_be_bytes();
+            let value = transaction.read(&key);
+            transaction.note_read(&key, value.clone());
+            let value = value
+                .map(|v| u64::from_be_bytes(v.try_into().unwrap()))
+                .unwrap_or(0);
+            transaction.write(&key, Some(&(value + 1).to_be_bytes()));
+        }
+
+        // Unwind the checkpoints we just pushed, innermost first, reverting
+        // a random subset and discarding (keeping) the rest.
+        while let Some(checkpoint) = checkpoints.pop() {
+            if rng.gen_bool(self.revert_rate) {
+                transaction.revert_to(checkpoint);
+            } else {
+                transaction.discard_checkpoint(checkpoint);
+            }
+        }
+    }
+}
+
+/// Exercises per-account storage rather than flat balances: `size`
+/// accounts each with `slots_per_account` contract storage slots, written
+/// in full on init, then mutated a random subset at a time.
+pub struct ContractStorageWorkload {
+    size: u64,
+    slots_per_account: u64,
+    ops_remaining: u64,
+    init: bool,
+}
+
+impl ContractStorageWorkload {
+    pub fn new_init(size: u64, slots_per_account: u64) -> Self {
+        ContractStorageWorkload {
+            size,
+            slots_per_account,
+            ops_remaining: size,
+            init: true,
+        }
+    }
+
+    pub fn new(size: u64, slots_per_account: u64, max_ops: u64) -> Self {
+        ContractStorageWorkload {
+            size,
+            slots_per_account,
+            ops_remaining: max_ops,
+            init: false,
+        }
+    }
+}
+
+impl Workload for ContractStorageWorkload {
+    fn run_step(&mut self, transaction: &mut dyn Transaction) {
+        let mut rng = rand::thread_rng();
+
+        if self.init {
+            for i in 0..self.size {
+                let addr = i.to_be_bytes();
+                for slot in 0..self.slots_per_account {
+                    let slot = slot.to_be_bytes();
+                    transaction.write_slot(&addr, &slot, Some(&1u64.to_be_bytes()));
+                }
+            }
+            self.init = false;
+            return;
+        }
+
+        if self.ops_remaining == 0 {
+            return;
+        }
+        self.ops_remaining -= 1;
+
+        let addr = rng.gen_range(0..self.size).to_be_bytes();
+        let mutated_slots = rng.gen_range(1..=self.slots_per_account);
+        for _ in 0..mutated_slots {
+            let slot = rng.gen_range(0..self.slots_per_account).to_be_bytes();
+            let value = transaction.read_slot(&addr, &slot);
+            transaction.note_read_slot(&addr, &slot, value.clone());
+            let value = value
+                .map(|v| u64::from_be_bytes(v.try_into().unwrap()))
+                .unwrap_or(0);
+            transaction.write_slot(&addr, &slot, Some(&(value + 1).to_be_bytes()));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod backend;
+mod cache;
 mod cli;
 mod custom_workload;
 mod nomt;
@@ -10,8 +11,9 @@ mod workload;
 mod reth;
 
 use anyhow::Result;
+use backend::Database;
 use clap::Parser;
-use cli::{Cli, Commands, InitParams, RunParams};
+use cli::{Cli, Commands, InitParams, RestoreParams, RunParams, SnapshotParams};
 use timer::Timer;
 
 pub fn main() -> Result<()> {
@@ -20,6 +22,8 @@ pub fn main() -> Result<()> {
     match cli.command {
         Commands::Init(params) => init(params),
         Commands::Run(params) => run(params),
+        Commands::Snapshot(params) => snapshot(params),
+        Commands::Restore(params) => restore(params),
     }
 }
 
@@ -32,8 +36,9 @@ pub fn init(params: InitParams) -> Result<()> {
         workload_params.commit_concurrency,
         workload_params.io_workers,
         workload_params.hashtable_buckets,
+        workload_params.state_cache_bytes,
     );
-    db.execute(None, &mut *init, None);
+    db.execute(None, &mut *init, None, false);
 
     Ok(())
 }
@@ -50,16 +55,17 @@ pub fn run(params: RunParams) -> Result<()> {
         workload_params.commit_concurrency,
         workload_params.io_workers,
         workload_params.hashtable_buckets,
+        workload_params.state_cache_bytes,
     );
 
     if params.reset {
-        db.execute(None, &mut *init, None);
+        db.execute(None, &mut *init, None, false);
     }
 
     let mut timer = Timer::new(format!("{}", params.backend));
     let warmup_timeout = params
         .warm_up
-        .map(|time_limit| std::time::Instant::now() + *time_limit);
+        .map(|time_limit| std::time::Instant::now() + time_limit);
 
     let thread_pool = rayon::ThreadPoolBuilder::new()
         .thread_name(|_| "benchtop-workload".into())
@@ -68,9 +74,9 @@ pub fn run(params: RunParams) -> Result<()> {
 
     if let Some(t) = warmup_timeout {
         if workload_params.workload_concurrency == 1 {
-            db.execute(Some(&mut timer), &mut *workloads[0], Some(t));
+            db.execute(Some(&mut timer), &mut *workloads[0], Some(t), params.witness);
         } else {
-            db.parallel_execute(Some(&mut timer), &thread_pool, &mut workloads, Some(t))?;
+            db.parallel_execute(Some(&mut timer), &thread_pool, &mut workloads, Some(t), params.witness)?;
         };
 
         timer = Timer::new(format!("{}", params.backend));
@@ -79,12 +85,12 @@ pub fn run(params: RunParams) -> Result<()> {
     let timeout = params
         .limits
         .time
-        .map(|time_limit| std::time::Instant::now() + *time_limit);
+        .map(|time_limit| std::time::Instant::now() + time_limit);
 
     if workload_params.workload_concurrency == 1 {
-        db.execute(Some(&mut timer), &mut *workloads[0], timeout);
+        db.execute(Some(&mut timer), &mut *workloads[0], timeout, params.witness);
     } else {
-        db.parallel_execute(Some(&mut timer), &thread_pool, &mut workloads, timeout)?;
+        db.parallel_execute(Some(&mut timer), &thread_pool, &mut workloads, timeout, params.witness)?;
     };
 
     db.print_metrics();
@@ -92,3 +98,13 @@ pub fn run(params: RunParams) -> Result<()> {
 
     Ok(())
 }
+
+pub fn snapshot(params: SnapshotParams) -> Result<()> {
+    let db = params.backend.instantiate(false, 1, 1, 1_000_000, None);
+    db.snapshot(&params.path, params.incremental.as_deref())
+}
+
+pub fn restore(params: RestoreParams) -> Result<()> {
+    let mut db = params.backend.instantiate(false, 1, 1, 1_000_000, None);
+    db.restore(&params.path, params.base.as_deref())
+}
@@ -1,9 +1,20 @@
-use crate::{backend::Transaction, timer::Timer, workload::Workload};
+use crate::{
+    backend::{
+        classify_write, copy_changed_since, copy_dir_all, CheckpointId, Database as BenchDatabase,
+        DirtyStats, Transaction, WitnessStats,
+    },
+    cache::StateCache,
+    timer::Timer,
+    workload::Workload,
+};
 
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
 use reth_db::{cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW}, init_db, mdbx::DatabaseArguments, tables, transaction::{DbTx, DbTxMut}, Database, DatabaseEnv};
 use reth_primitives_traits::{Account, StorageEntry};
-use reth_trie::{hashed_cursor::HashedCursor, proof::Proof, updates::TrieUpdates, HashedPostState, HashedPostStateSorted, StateRoot, StoredNibbles};
-use reth_trie_db::{DatabaseHashedAccountCursor, DatabaseProof, DatabaseStateRoot};
+use reth_trie::{hashed_cursor::HashedCursor, proof::Proof, updates::TrieUpdates, HashedPostState, HashedPostStateSorted, HashedStorage, StateRoot, StoredNibbles};
+use reth_trie_db::{DatabaseHashedAccountCursor, DatabaseHashedStorageCursor, DatabaseProof, DatabaseStateRoot};
 use alloy_primitives::{keccak256, FixedBytes, U256};
 use itertools::Itertools;
 
@@ -102,88 +113,384 @@ impl<Tx: DbTxMut + DbTx> UpdateWriter<Tx> {
 
 pub struct RethDB {
     pub database_env: DatabaseEnv,
+    witness_stats: WitnessStats,
+    dirty_stats: DirtyStats,
+    state_cache: Option<StateCache>,
 }
 
 impl RethDB {
-    pub fn open(reset: bool) -> Self {
+    pub fn open(reset: bool, state_cache_bytes: Option<usize>) -> Self {
         if reset {
             // Delete previously existing db
             let _ = std::fs::remove_dir_all(RETH_DB_FOLDER);
         }
 
         Self {
-            database_env: init_db(RETH_DB_FOLDER, DatabaseArguments::default()).unwrap()  
+            database_env: init_db(RETH_DB_FOLDER, DatabaseArguments::default()).unwrap(),
+            witness_stats: WitnessStats::default(),
+            dirty_stats: DirtyStats::default(),
+            state_cache: state_cache_bytes.map(StateCache::new),
         }
     }
 
-    pub fn execute(&mut self, mut timer: Option<&mut Timer>, workload: &mut dyn Workload) {
-        let _timer_guard_total = timer.as_mut().map(|t| t.record_span("workload"));
+}
+
+impl BenchDatabase for RethDB {
+    fn execute(
+        &mut self,
+        timer: Option<&mut Timer>,
+        workload: &mut dyn Workload,
+        _timeout: Option<std::time::Instant>,
+        witness: bool,
+    ) {
         let db_tx = self.database_env.tx().unwrap();
 
-        let db_cursor = db_tx.new_cursor::<tables::HashedAccounts>().unwrap();
-        let account_cursor = DatabaseHashedAccountCursor::new(db_cursor);
         let mut transaction = RethTx {
-            timer: timer,
-            memory: HashedPostState::default(),
-            persistent: Box::new(account_cursor),
-            reads: HashSet::new(),
+            timer,
+            memory: vec![HashedPostState::default()],
+            tx: &db_tx,
+            reads: vec![HashSet::new()],
+            state_cache: &mut self.state_cache,
+            original: HashMap::new(),
+            dirty_stats: DirtyStats::default(),
         };
-        workload.run_step(&mut transaction);
+        {
+            let _timer_guard = transaction.timer.as_mut().map(|t| t.record_span("workload"));
+            workload.run_step(&mut transaction);
+        }
+        self.dirty_stats += transaction.dirty_stats();
+
+        // Pull the owned parts out of `transaction` now, ending its borrow
+        // of `self.state_cache` (and of `db_tx`) so the commit below can
+        // touch `self`/`db_tx` directly, and so the "commit" and "prove"
+        // spans can each take their own borrow of `timer` in turn instead
+        // of two overlapping borrows through `transaction.timer`.
+        let RethTx {
+            mut timer,
+            memory,
+            reads,
+            ..
+        } = transaction;
+
+        let memory = memory
+            .into_iter()
+            .reduce(|mut base, overlay| {
+                base.extend(overlay);
+                base
+            })
+            .unwrap_or_default();
 
+        let _timer_guard_commit = timer.as_mut().map(|t| t.record_span("commit"));
 
-        let _timer_guard_commit = transaction.timer.as_mut().map(|t| t.record_span("commit_and_prove"));
+        if let Some(cache) = self.state_cache.as_mut() {
+            for (hashed_key, account) in memory.accounts.iter() {
+                match account {
+                    Some(account) => {
+                        let balance_vec = account.balance.to_be_bytes_vec();
+                        cache.insert(
+                            hashed_key.to_vec(),
+                            balance_vec[balance_vec.len() - 8..].to_vec(),
+                        );
+                    }
+                    None => cache.invalidate(hashed_key.as_slice()),
+                }
+            }
+
+            for (hashed_address, storage) in memory.storages.iter() {
+                for (hashed_slot, value) in storage.storage.iter() {
+                    let cache_key = [hashed_address.as_slice(), hashed_slot.as_slice()].concat();
+                    if storage.wiped || value.is_zero() {
+                        cache.invalidate(&cache_key);
+                    } else {
+                        let value_vec = value.to_be_bytes_vec();
+                        cache.insert(cache_key, value_vec[value_vec.len() - 8..].to_vec());
+                    }
+                }
+            }
+        }
 
-        let res = StateRoot::overlay_root_with_updates(&db_tx, transaction.memory.clone()).unwrap();
+        let res = StateRoot::overlay_root_with_updates(&db_tx, memory.clone()).unwrap();
         let rw_tx = self.database_env.tx_mut().unwrap();
         UpdateWriter::write_trie_updates(&rw_tx, &res.1);
-        UpdateWriter::write_hashed_state(&rw_tx, &transaction.memory.into_sorted());
+        UpdateWriter::write_hashed_state(&rw_tx, &memory.into_sorted());
         rw_tx.inner.commit().unwrap();
+        drop(_timer_guard_commit);
+
+        if witness {
+            let reads = reads
+                .into_iter()
+                .reduce(|mut base, overlay| {
+                    base.extend(overlay);
+                    base
+                })
+                .unwrap_or_default();
+
+            let _timer_guard_prove = timer.as_mut().map(|t| t.record_span("prove"));
+            self.witness_stats += self.prove(&reads);
+        }
+    }
+
+    fn parallel_execute(
+        &mut self,
+        mut timer: Option<&mut Timer>,
+        _thread_pool: &rayon::ThreadPool,
+        workloads: &mut [Box<dyn Workload>],
+        timeout: Option<std::time::Instant>,
+        witness: bool,
+    ) -> Result<()> {
+        for workload in workloads {
+            BenchDatabase::execute(self, timer.as_deref_mut(), &mut **workload, timeout, witness);
+        }
+        Ok(())
+    }
+
+    fn print_metrics(&self) {
+        self.witness_stats.print("reth");
+        self.dirty_stats.print("reth");
+        if let Some(cache) = self.state_cache.as_ref() {
+            cache.print("reth");
+        }
+    }
 
+    fn prove(&self, reads: &HashSet<Vec<u8>>) -> WitnessStats {
         let db_tx = self.database_env.tx().unwrap();
         let proof = Proof::from_tx(&db_tx);
 
-        let targets = transaction.reads.iter().map(|k| (*k, HashSet::new())).collect();
-        let _ = proof.multiproof(targets);
+        // A plain account read is its 32-byte hashed address; a slot read
+        // is the 64-byte concatenation of hashed address and hashed slot
+        // (see `RethTx::reads`). Fold both into the per-account storage
+        // slot targets `multiproof` expects.
+        let mut targets: HashMap<FixedBytes<32>, HashSet<FixedBytes<32>>> = HashMap::new();
+        for key in reads {
+            match key.len() {
+                32 => {
+                    targets.entry(FixedBytes::<32>::from_slice(key)).or_default();
+                }
+                64 => {
+                    let account = FixedBytes::<32>::from_slice(&key[..32]);
+                    let slot = FixedBytes::<32>::from_slice(&key[32..]);
+                    targets.entry(account).or_default().insert(slot);
+                }
+                _ => {}
+            }
+        }
+        let multiproof = proof.multiproof(targets).unwrap();
+
+        let account_bytes: usize = multiproof
+            .account_subtree
+            .iter()
+            .map(|(_, node)| node.len())
+            .sum();
+        let storage_bytes: usize = multiproof
+            .storages
+            .values()
+            .flat_map(|storage_proof| storage_proof.subtree.iter())
+            .map(|(_, node)| node.len())
+            .sum();
+        WitnessStats::single(account_bytes + storage_bytes)
+    }
+
+    fn snapshot(&self, path: &Path, incremental_base: Option<&Path>) -> Result<()> {
+        match incremental_base {
+            None => copy_dir_all(Path::new(RETH_DB_FOLDER), path),
+            Some(base) => copy_changed_since(Path::new(RETH_DB_FOLDER), base, path),
+        }
+    }
+
+    fn restore(&mut self, path: &Path, base: Option<&Path>) -> Result<()> {
+        // Remove the current env's files; the open env is replaced below
+        // once the restored files are in place.
+        let _ = std::fs::remove_dir_all(RETH_DB_FOLDER);
+        if let Some(base) = base {
+            copy_dir_all(base, Path::new(RETH_DB_FOLDER))?;
+        }
+        copy_dir_all(path, Path::new(RETH_DB_FOLDER))?;
+        self.database_env = init_db(RETH_DB_FOLDER, DatabaseArguments::default())?;
+        Ok(())
     }
 }
 
-pub struct RethTx<'a> {
+pub struct RethTx<'a, Tx: DbTx> {
     timer: Option<&'a mut Timer>,
-    memory: HashedPostState,
-    persistent: Box<dyn HashedCursor<Value = Account>>,
-    reads: HashSet<FixedBytes<32>>
+    // A stack of write overlays; `checkpoint` pushes a new one and reads
+    // consult it top-to-bottom before falling through to the db tx.
+    memory: Vec<HashedPostState>,
+    tx: &'a Tx,
+    // Same 32-vs-64-byte key shape as `original` below: a plain account
+    // read is its 32-byte hashed address, a slot read the 64-byte
+    // concatenation of hashed address and hashed slot. `prove` splits on
+    // that to build per-account storage proof targets.
+    reads: Vec<HashSet<Vec<u8>>>,
+    state_cache: &'a mut Option<StateCache>,
+    // Value each account/slot key held the first time this step wrote it,
+    // i.e. at step start; used to classify writes in `dirty_stats`. Account
+    // keys are 32-byte hashed addresses, slot keys the 64-byte
+    // concatenation used by `read_slot`/`write_slot`, so the two never
+    // collide.
+    original: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    dirty_stats: DirtyStats,
 }
 
-impl<'a> Transaction for RethTx<'a> {
+impl<'a, Tx: DbTx> RethTx<'a, Tx> {
+    // Like `read`, but bypasses the state cache so that snapshotting a
+    // key's pre-write value for `dirty_stats` doesn't itself count as a
+    // cache hit/miss.
+    fn original_balance(&self, hashed_key: FixedBytes<32>) -> Option<Vec<u8>> {
+        for overlay in self.memory.iter().rev() {
+            if let Some(acc) = overlay.accounts.get(&hashed_key) {
+                return acc.as_ref().map(|acc| {
+                    let balance_vec = acc.balance.to_be_bytes_vec();
+                    balance_vec[balance_vec.len() - 8..].to_vec()
+                });
+            }
+        }
+
+        let db_cursor = self.tx.new_cursor::<tables::HashedAccounts>().unwrap();
+        let mut account_cursor = DatabaseHashedAccountCursor::new(db_cursor);
+        match account_cursor.seek(hashed_key) {
+            Ok(Some((key, account))) if key.eq(&hashed_key) => {
+                let balance_vec = account.balance.to_be_bytes_vec();
+                Some(balance_vec[balance_vec.len() - 8..].to_vec())
+            }
+            _ => None,
+        }
+    }
+
+    // Like `read_slot`, but bypasses the state cache; see `original_balance`.
+    fn original_slot_value(
+        &self,
+        hashed_address: FixedBytes<32>,
+        hashed_slot: FixedBytes<32>,
+    ) -> Option<Vec<u8>> {
+        for overlay in self.memory.iter().rev() {
+            if let Some(storage) = overlay.storages.get(&hashed_address) {
+                if let Some(value) = storage.storage.get(&hashed_slot) {
+                    let value_vec = value.to_be_bytes_vec();
+                    return Some(value_vec[value_vec.len() - 8..].to_vec());
+                }
+                if storage.wiped {
+                    return None;
+                }
+            }
+        }
+
+        let db_cursor = self.tx.new_dup_cursor::<tables::HashedStorages>().unwrap();
+        let mut storage_cursor = DatabaseHashedStorageCursor::new(db_cursor, hashed_address);
+        match storage_cursor.seek(hashed_slot) {
+            Ok(Some((slot_key, value))) if slot_key == hashed_slot => {
+                let value_vec = value.to_be_bytes_vec();
+                Some(value_vec[value_vec.len() - 8..].to_vec())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a, Tx: DbTx> Transaction for RethTx<'a, Tx> {
     fn read(&mut self, key: &[u8]) -> Option<Vec<u8>> {
         let _timer_guard_read = self.timer.as_mut().map(|t| t.record_span("read"));
         let hashed_key = keccak256(key);
-        let acc = self.memory.accounts.get(&hashed_key);
-        if let Some(Some(acc)) = acc {
-            let balance_vec = acc.balance.to_be_bytes_vec();
-            return Some(balance_vec[balance_vec.len()-8..].to_vec());
+
+        for overlay in self.memory.iter().rev() {
+            if let Some(acc) = overlay.accounts.get(&hashed_key) {
+                return acc.as_ref().map(|acc| {
+                    let balance_vec = acc.balance.to_be_bytes_vec();
+                    balance_vec[balance_vec.len() - 8..].to_vec()
+                });
+            }
+        }
+
+        if let Some(cache) = self.state_cache.as_mut() {
+            if let Some(value) = cache.get(hashed_key.as_slice()) {
+                return Some(value);
+            }
         }
 
-        match self.persistent.seek(hashed_key) {
+        let db_cursor = self.tx.new_cursor::<tables::HashedAccounts>().unwrap();
+        let mut account_cursor = DatabaseHashedAccountCursor::new(db_cursor);
+        let value = match account_cursor.seek(hashed_key) {
             Ok(Some((key, account))) => {
                 if key.eq(&hashed_key) {
-                    let balance_vec = account.balance.to_be_bytes_vec();        
+                    let balance_vec = account.balance.to_be_bytes_vec();
                     Some(balance_vec[balance_vec.len()-8..].to_vec())
                 } else {
                     None
                 }
             }
             _ => None
+        };
+
+        if let (Some(cache), Some(value)) = (self.state_cache.as_mut(), value.as_ref()) {
+            cache.insert(hashed_key.to_vec(), value.clone());
         }
+        value
+    }
+
+    fn read_slot(&mut self, addr: &[u8], slot: &[u8]) -> Option<Vec<u8>> {
+        let _timer_guard_read = self.timer.as_mut().map(|t| t.record_span("read"));
+        let hashed_address = keccak256(addr);
+        let hashed_slot = keccak256(slot);
+        let cache_key = [hashed_address.as_slice(), hashed_slot.as_slice()].concat();
+
+        for overlay in self.memory.iter().rev() {
+            if let Some(storage) = overlay.storages.get(&hashed_address) {
+                if let Some(value) = storage.storage.get(&hashed_slot) {
+                    let value_vec = value.to_be_bytes_vec();
+                    return Some(value_vec[value_vec.len() - 8..].to_vec());
+                }
+                if storage.wiped {
+                    return None;
+                }
+            }
+        }
+
+        if let Some(cache) = self.state_cache.as_mut() {
+            if let Some(value) = cache.get(&cache_key) {
+                return Some(value);
+            }
+        }
+
+        let db_cursor = self.tx.new_dup_cursor::<tables::HashedStorages>().unwrap();
+        let mut storage_cursor = DatabaseHashedStorageCursor::new(db_cursor, hashed_address);
+        let value = match storage_cursor.seek(hashed_slot) {
+            Ok(Some((slot_key, value))) if slot_key == hashed_slot => {
+                let value_vec = value.to_be_bytes_vec();
+                Some(value_vec[value_vec.len() - 8..].to_vec())
+            }
+            _ => None,
+        };
+
+        if let (Some(cache), Some(value)) = (self.state_cache.as_mut(), value.as_ref()) {
+            cache.insert(cache_key, value.clone());
+        }
+        value
     }
 
     fn note_read(&mut self, key: &[u8], _: Option<Vec<u8>>) {
-        self.reads.insert(keccak256(key));
+        self.reads
+            .last_mut()
+            .expect("checkpoint stack is never empty")
+            .insert(keccak256(key).to_vec());
+    }
+
+    fn note_read_slot(&mut self, addr: &[u8], slot: &[u8], _: Option<Vec<u8>>) {
+        let hashed_address = keccak256(addr);
+        let hashed_slot = keccak256(slot);
+        self.reads
+            .last_mut()
+            .expect("checkpoint stack is never empty")
+            .insert([hashed_address.as_slice(), hashed_slot.as_slice()].concat());
     }
 
     fn write(&mut self, key: &[u8], value: Option<&[u8]>) {
         let hashed_key = keccak256(key);
-        
+
+        if !self.original.contains_key(hashed_key.as_slice()) {
+            let original = self.original_balance(hashed_key);
+            self.original.insert(hashed_key.to_vec(), original);
+        }
+        let original = self.original.get(hashed_key.as_slice()).unwrap().as_deref();
+        classify_write(original, value, &mut self.dirty_stats);
 
         let acc = if let Some(value) = value {
             Some(Account {
@@ -194,6 +501,62 @@ impl<'a> Transaction for RethTx<'a> {
         } else {
             None
         };
-        self.memory.accounts.insert(hashed_key, acc);
+        self.memory
+            .last_mut()
+            .expect("checkpoint stack is never empty")
+            .accounts
+            .insert(hashed_key, acc);
+    }
+
+    fn write_slot(&mut self, addr: &[u8], slot: &[u8], value: Option<&[u8]>) {
+        let hashed_address = keccak256(addr);
+        let hashed_slot = keccak256(slot);
+        let dirty_key = [hashed_address.as_slice(), hashed_slot.as_slice()].concat();
+
+        if !self.original.contains_key(&dirty_key) {
+            let original = self.original_slot_value(hashed_address, hashed_slot);
+            self.original.insert(dirty_key.clone(), original);
+        }
+        let original = self.original.get(&dirty_key).unwrap().as_deref();
+        classify_write(original, value, &mut self.dirty_stats);
+
+        let value = value.map(U256::from_be_slice).unwrap_or_default();
+
+        self.memory
+            .last_mut()
+            .expect("checkpoint stack is never empty")
+            .storages
+            .entry(hashed_address)
+            .or_insert_with(|| HashedStorage::new(false))
+            .storage
+            .insert(hashed_slot, value);
+    }
+
+    fn checkpoint(&mut self) -> CheckpointId {
+        let _timer_guard = self.timer.as_mut().map(|t| t.record_span("checkpoint"));
+        let id = self.memory.len();
+        self.memory.push(HashedPostState::default());
+        self.reads.push(HashSet::new());
+        id
+    }
+
+    fn revert_to(&mut self, checkpoint: CheckpointId) {
+        let _timer_guard = self.timer.as_mut().map(|t| t.record_span("revert"));
+        self.memory.truncate(checkpoint);
+        self.reads.truncate(checkpoint);
+    }
+
+    fn discard_checkpoint(&mut self, checkpoint: CheckpointId) {
+        let overlay = self.memory.remove(checkpoint);
+        self.memory[checkpoint - 1].extend(overlay);
+        let reads = self.reads.remove(checkpoint);
+        self.reads[checkpoint - 1].extend(reads);
+    }
+
+    fn dirty_stats(&self) -> DirtyStats {
+        DirtyStats {
+            dirty_keys: self.original.len() as u64,
+            ..self.dirty_stats
+        }
     }
 }
\ No newline at end of file
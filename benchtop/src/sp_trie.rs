@@ -0,0 +1,262 @@
+use crate::backend::{
+    classify_write, read_kv_snapshot, write_kv_snapshot, write_kv_snapshot_incremental,
+    CheckpointId, Database, DirtyStats, Transaction, WitnessStats,
+};
+use crate::cache::StateCache;
+use crate::timer::Timer;
+use crate::workload::Workload;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+const SP_TRIE_DB_FOLDER: &str = "sp_trie_db";
+
+pub struct SpTrieDB {
+    persistent: BTreeMap<Vec<u8>, Vec<u8>>,
+    witness_stats: WitnessStats,
+    dirty_stats: DirtyStats,
+    state_cache: Option<StateCache>,
+}
+
+impl SpTrieDB {
+    pub fn open(reset: bool, state_cache_bytes: Option<usize>) -> Self {
+        if reset {
+            let _ = std::fs::remove_dir_all(SP_TRIE_DB_FOLDER);
+        }
+
+        let persistent = read_kv_snapshot(Path::new(SP_TRIE_DB_FOLDER)).unwrap_or_default();
+
+        SpTrieDB {
+            persistent,
+            witness_stats: WitnessStats::default(),
+            dirty_stats: DirtyStats::default(),
+            state_cache: state_cache_bytes.map(StateCache::new),
+        }
+    }
+}
+
+impl Database for SpTrieDB {
+    fn execute(
+        &mut self,
+        timer: Option<&mut Timer>,
+        workload: &mut dyn Workload,
+        _timeout: Option<std::time::Instant>,
+        witness: bool,
+    ) {
+        let mut transaction = SpTrieTx {
+            timer,
+            memory: vec![BTreeMap::new()],
+            reads: vec![HashSet::new()],
+            persistent: &self.persistent,
+            state_cache: &mut self.state_cache,
+            original: HashMap::new(),
+            dirty_stats: DirtyStats::default(),
+        };
+        {
+            let _timer_guard = transaction.timer.as_mut().map(|t| t.record_span("workload"));
+            workload.run_step(&mut transaction);
+        }
+        self.dirty_stats += transaction.dirty_stats();
+
+        // Pull the owned parts out of `transaction` now, ending its borrows
+        // of `self.persistent`/`self.state_cache` so the commit below can
+        // touch them directly.
+        let SpTrieTx {
+            mut timer,
+            memory,
+            reads,
+            ..
+        } = transaction;
+
+        let merged = memory
+            .into_iter()
+            .reduce(|mut acc, overlay| {
+                acc.extend(overlay);
+                acc
+            })
+            .unwrap_or_default();
+
+        let _timer_guard_commit = timer.as_mut().map(|t| t.record_span("commit"));
+        for (key, value) in merged {
+            match value {
+                Some(value) => {
+                    if let Some(cache) = self.state_cache.as_mut() {
+                        cache.insert(key.clone(), value.clone());
+                    }
+                    self.persistent.insert(key, value);
+                }
+                None => {
+                    if let Some(cache) = self.state_cache.as_mut() {
+                        cache.invalidate(&key);
+                    }
+                    self.persistent.remove(&key);
+                }
+            }
+        }
+
+        let _ = write_kv_snapshot(Path::new(SP_TRIE_DB_FOLDER), &self.persistent);
+        drop(_timer_guard_commit);
+
+        if witness {
+            let reads = reads
+                .into_iter()
+                .reduce(|mut acc, overlay| {
+                    acc.extend(overlay);
+                    acc
+                })
+                .unwrap_or_default();
+
+            let _timer_guard_prove = timer.as_mut().map(|t| t.record_span("prove"));
+            self.witness_stats += self.prove(&reads);
+        }
+    }
+
+    fn parallel_execute(
+        &mut self,
+        mut timer: Option<&mut Timer>,
+        _thread_pool: &rayon::ThreadPool,
+        workloads: &mut [Box<dyn Workload>],
+        timeout: Option<std::time::Instant>,
+        witness: bool,
+    ) -> Result<()> {
+        for workload in workloads {
+            self.execute(timer.as_deref_mut(), &mut **workload, timeout, witness);
+        }
+        Ok(())
+    }
+
+    fn print_metrics(&self) {
+        println!("sp_trie: {} keys", self.persistent.len());
+        self.witness_stats.print("sp_trie");
+        self.dirty_stats.print("sp_trie");
+        if let Some(cache) = self.state_cache.as_ref() {
+            cache.print("sp_trie");
+        }
+    }
+
+    fn snapshot(&self, path: &Path, incremental_base: Option<&Path>) -> Result<()> {
+        match incremental_base {
+            None => write_kv_snapshot(path, &self.persistent),
+            Some(base) => write_kv_snapshot_incremental(path, base, &self.persistent),
+        }
+    }
+
+    fn restore(&mut self, path: &Path, base: Option<&Path>) -> Result<()> {
+        let mut persistent = match base {
+            Some(base) => read_kv_snapshot(base)?,
+            None => BTreeMap::new(),
+        };
+        persistent.extend(read_kv_snapshot(path)?);
+        self.persistent = persistent;
+        write_kv_snapshot(Path::new(SP_TRIE_DB_FOLDER), &self.persistent)
+    }
+
+    // No real trie here: the "witness" is just the key/value pairs a
+    // verifier would need, which is the honest analogue of a multiproof
+    // for a flat key-value store.
+    fn prove(&self, reads: &HashSet<Vec<u8>>) -> WitnessStats {
+        let bytes: usize = reads
+            .iter()
+            .map(|key| key.len() + self.persistent.get(key).map_or(0, Vec::len))
+            .sum();
+        WitnessStats::single(bytes)
+    }
+}
+
+struct SpTrieTx<'a> {
+    timer: Option<&'a mut Timer>,
+    memory: Vec<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+    reads: Vec<HashSet<Vec<u8>>>,
+    persistent: &'a BTreeMap<Vec<u8>, Vec<u8>>,
+    state_cache: &'a mut Option<StateCache>,
+    // Value each key held the first time this step wrote it, i.e. at
+    // step start; used to classify writes in `dirty_stats`.
+    original: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    dirty_stats: DirtyStats,
+}
+
+impl<'a> SpTrieTx<'a> {
+    // Like `read`, but bypasses the state cache so that snapshotting a
+    // key's pre-write value for `dirty_stats` doesn't itself count as a
+    // cache hit/miss.
+    fn original_value(&self, key: &[u8]) -> Option<Vec<u8>> {
+        for overlay in self.memory.iter().rev() {
+            if let Some(value) = overlay.get(key) {
+                return value.clone();
+            }
+        }
+        self.persistent.get(key).cloned()
+    }
+}
+
+impl<'a> Transaction for SpTrieTx<'a> {
+    fn read(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let _timer_guard = self.timer.as_mut().map(|t| t.record_span("read"));
+        for overlay in self.memory.iter().rev() {
+            if let Some(value) = overlay.get(key) {
+                return value.clone();
+            }
+        }
+
+        if let Some(cache) = self.state_cache.as_mut() {
+            if let Some(value) = cache.get(key) {
+                return Some(value);
+            }
+        }
+
+        let value = self.persistent.get(key).cloned();
+        if let (Some(cache), Some(value)) = (self.state_cache.as_mut(), value.as_ref()) {
+            cache.insert(key.to_vec(), value.clone());
+        }
+        value
+    }
+
+    fn note_read(&mut self, key: &[u8], _: Option<Vec<u8>>) {
+        self.reads
+            .last_mut()
+            .expect("checkpoint stack is never empty")
+            .insert(key.to_vec());
+    }
+
+    fn write(&mut self, key: &[u8], value: Option<&[u8]>) {
+        if !self.original.contains_key(key) {
+            let original = self.original_value(key);
+            self.original.insert(key.to_vec(), original);
+        }
+        let original = self.original.get(key).unwrap().as_deref();
+        classify_write(original, value, &mut self.dirty_stats);
+
+        self.memory
+            .last_mut()
+            .expect("checkpoint stack is never empty")
+            .insert(key.to_vec(), value.map(|v| v.to_vec()));
+    }
+
+    fn checkpoint(&mut self) -> CheckpointId {
+        let _timer_guard = self.timer.as_mut().map(|t| t.record_span("checkpoint"));
+        let id = self.memory.len();
+        self.memory.push(BTreeMap::new());
+        self.reads.push(HashSet::new());
+        id
+    }
+
+    fn revert_to(&mut self, checkpoint: CheckpointId) {
+        let _timer_guard = self.timer.as_mut().map(|t| t.record_span("revert"));
+        self.memory.truncate(checkpoint);
+        self.reads.truncate(checkpoint);
+    }
+
+    fn discard_checkpoint(&mut self, checkpoint: CheckpointId) {
+        let overlay = self.memory.remove(checkpoint);
+        self.memory[checkpoint - 1].extend(overlay);
+        let reads = self.reads.remove(checkpoint);
+        self.reads[checkpoint - 1].extend(reads);
+    }
+
+    fn dirty_stats(&self) -> DirtyStats {
+        DirtyStats {
+            dirty_keys: self.original.len() as u64,
+            ..self.dirty_stats
+        }
+    }
+}
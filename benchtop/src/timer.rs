@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Accumulates wall-clock time spent in named spans over the course of a
+/// run, keyed by span label (e.g. `"read"`, `"commit"`).
+pub struct Timer {
+    name: String,
+    spans: BTreeMap<&'static str, Duration>,
+}
+
+impl Timer {
+    pub fn new(name: String) -> Self {
+        Timer {
+            name,
+            spans: BTreeMap::new(),
+        }
+    }
+
+    /// Start timing `label`; the returned guard adds its elapsed time to
+    /// the span's running total when dropped.
+    pub fn record_span(&mut self, label: &'static str) -> SpanGuard<'_> {
+        SpanGuard {
+            timer: self,
+            label,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn print(&self, size: u64) {
+        println!("== {} ==", self.name);
+        for (label, duration) in &self.spans {
+            println!("  {label}: {duration:?} total ({size} keys)");
+        }
+    }
+}
+
+pub struct SpanGuard<'a> {
+    timer: &'a mut Timer,
+    label: &'static str,
+    start: Instant,
+}
+
+impl Drop for SpanGuard<'_> {
+    fn drop(&mut self) {
+        *self.timer.spans.entry(self.label).or_default() += self.start.elapsed();
+    }
+}
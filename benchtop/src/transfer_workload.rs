@@ -0,0 +1,68 @@
+use crate::backend::Transaction;
+use crate::workload::Workload;
+use rand::Rng;
+
+/// Moves a random amount between two random accounts out of `size` total,
+/// the standard "transfer" benchmark workload.
+pub struct TransferWorkload {
+    size: u64,
+    ops_remaining: u64,
+    init: bool,
+}
+
+impl TransferWorkload {
+    pub fn new_init(size: u64) -> Self {
+        TransferWorkload {
+            size,
+            ops_remaining: size,
+            init: true,
+        }
+    }
+
+    pub fn new(size: u64, max_ops: u64) -> Self {
+        TransferWorkload {
+            size,
+            ops_remaining: max_ops,
+            init: false,
+        }
+    }
+}
+
+impl Workload for TransferWorkload {
+    fn run_step(&mut self, transaction: &mut dyn Transaction) {
+        let mut rng = rand::thread_rng();
+
+        if self.init {
+            for i in 0..self.size {
+                let key = i.to_be_bytes();
+                transaction.write(&key, Some(&1_000_000u64.to_be_bytes()));
+            }
+            self.init = false;
+            return;
+        }
+
+        if self.ops_remaining == 0 {
+            return;
+        }
+        self.ops_remaining -= 1;
+
+        let from = rng.gen_range(0..self.size).to_be_bytes();
+        let to = rng.gen_range(0..self.size).to_be_bytes();
+
+        let from_balance = transaction.read(&from);
+        transaction.note_read(&from, from_balance.clone());
+        let to_balance = transaction.read(&to);
+        transaction.note_read(&to, to_balance.clone());
+
+        let amount = 1u64;
+        let from_balance = from_balance
+            .map(|v| u64::from_be_bytes(v.try_into().unwrap()))
+            .unwrap_or(0);
+        let to_balance = to_balance
+            .map(|v| u64::from_be_bytes(v.try_into().unwrap()))
+            .unwrap_or(0);
+
+        transaction.write(&from, Some(&from_balance.saturating_sub(amount).to_be_bytes()));
+        transaction.write(&to, Some(&(to_balance + amount).to_be_bytes()));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::backend::Transaction;
+use crate::cli::WorkloadParams;
+use crate::custom_workload::{CheckpointWorkload, ContractStorageWorkload};
+use crate::transfer_workload::TransferWorkload;
+use anyhow::Result;
+
+/// A repeatable sequence of reads/writes driven against a backend's
+/// `Transaction` for a single step (one `init`/`run` call is one step,
+/// or many steps under `parallel_execute`).
+pub trait Workload {
+    fn run_step(&mut self, transaction: &mut dyn Transaction);
+}
+
+/// Build the `init` workload and `workload_concurrency` copies of the run
+/// workload described by `params`, each capped at `max_ops` operations.
+pub fn parse(
+    params: &WorkloadParams,
+    max_ops: u64,
+) -> Result<(Box<dyn Workload>, Vec<Box<dyn Workload>>)> {
+    let init: Box<dyn Workload> = match params.kind {
+        WorkloadKind::ContractStorage => Box::new(ContractStorageWorkload::new_init(
+            params.size,
+            params.slots_per_account,
+        )),
+        _ => Box::new(TransferWorkload::new_init(params.size)),
+    };
+
+    let workloads = (0..params.workload_concurrency)
+        .map(|_| -> Box<dyn Workload> {
+            match params.kind {
+                WorkloadKind::Transfer => Box::new(TransferWorkload::new(params.size, max_ops)),
+                WorkloadKind::Checkpoint => Box::new(CheckpointWorkload::new(
+                    params.size,
+                    max_ops,
+                    params.revert_rate,
+                )),
+                WorkloadKind::ContractStorage => Box::new(ContractStorageWorkload::new(
+                    params.size,
+                    params.slots_per_account,
+                    max_ops,
+                )),
+            }
+        })
+        .collect();
+
+    Ok((init, workloads))
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum, Default)]
+pub enum WorkloadKind {
+    #[default]
+    Transfer,
+    Checkpoint,
+    ContractStorage,
+}